@@ -1,44 +1,596 @@
+use html5ever::serialize::{serialize, SerializeOpts, TraversalScope};
+use html5ever::tendril::TendrilSink;
+use html5ever::{local_name, namespace_url, ns, parse_fragment, QualName};
+use markup5ever_rcdom::{Handle, NodeData, RcDom, SerializableHandle};
 use pulldown_cmark::{html, Options, Parser as MdParser};
+use std::collections::HashMap;
+use std::rc::Rc;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, Theme as SyntectTheme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+// Tags we keep when re-serializing user-authored HTML. Everything else is
+// stripped, but its text/allowed descendants survive in its place.
+const ALLOWED_TAGS: &[&str] = &[
+    "p", "h1", "h2", "h3", "h4", "h5", "h6", "a", "img", "code", "pre", "ul", "ol", "li", "table",
+    "thead", "tbody", "tfoot", "tr", "td", "th", "blockquote", "em", "strong", "del", "input",
+    "br", "hr", "span", "div",
+];
+
+// Tags whose content is never meaningful as page text (e.g. `<script>`
+// bodies are raw JS, not prose) — these are dropped along with their
+// children, instead of keeping the children the way other stripped tags do.
+const OPAQUE_TAGS: &[&str] = &["script", "style", "template"];
+
+fn allowed_attrs(tag: &str) -> &'static [&'static str] {
+    match tag {
+        "a" => &["class", "href", "title", "aria-label"],
+        "img" => &["class", "src", "alt", "title"],
+        "input" => &["class", "type", "disabled", "checked"],
+        // `id` lets the heading-anchor slugs (see `add_heading_anchors`) survive;
+        // `style` carries the per-heading `color:#rrggbb` the accent gradient
+        // (see `gradient_colors`) assigns when one is configured.
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => &["class", "id", "style"],
+        // `style` carries the inline `color:#rrggbb` syntect emits per token
+        // (see `highlight_code`); `class` still lets the fenced-code-block
+        // language tag through on `code`/`pre`.
+        "span" => &["class", "style"],
+        _ => &["class"],
+    }
+}
+
+// Rejects `javascript:`/`vbscript:`/`data:` (and anything else not explicitly
+// allowed) regardless of casing or embedded whitespace/control characters.
+fn is_safe_url(value: &str) -> bool {
+    let cleaned: String = value.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect();
+    let lower = cleaned.to_lowercase();
+    match lower.find(':') {
+        Some(colon) => {
+            let scheme = &lower[..colon];
+            let looks_like_scheme = !scheme.is_empty()
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.');
+            if looks_like_scheme {
+                matches!(scheme, "http" | "https" | "mailto")
+            } else {
+                // A colon that isn't part of a scheme (e.g. inside a relative path) is fine.
+                true
+            }
+        }
+        None => true,
+    }
+}
 
+// `input` is only allowlisted for task-list checkboxes (`input[type=checkbox
+// disabled]`); any other `type` (text, radio, file, ...), or a checkbox without
+// `disabled`, makes it a live form control the sanitizer is supposed to
+// exclude, so it doesn't count as "allowed" here.
+fn is_checkbox_input(handle: &Handle) -> bool {
+    if let NodeData::Element { attrs, .. } = &handle.data {
+        let attrs = attrs.borrow();
+        let is_checkbox = attrs
+            .iter()
+            .any(|attr| attr.name.local.as_ref() == "type" && attr.value.eq_ignore_ascii_case("checkbox"));
+        let is_disabled = attrs.iter().any(|attr| attr.name.local.as_ref() == "disabled");
+        is_checkbox && is_disabled
+    } else {
+        false
+    }
+}
+
+// `style` is only ever allowlisted (on `h1`-`h6` and `span`) to carry the
+// `color:#rrggbb` the accent gradient and syntax highlighter emit themselves
+// (see `gradient_colors`/`highlight_code`) - never arbitrary attacker-authored
+// CSS, which could exfiltrate data via `url(...)` or build a clickjacking
+// overlay with `position:fixed`. Accept only that exact generated shape.
+fn is_safe_style_value(value: &str) -> bool {
+    let Some(hex) = value.strip_prefix("color:#") else {
+        return false;
+    };
+    hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase())
+}
+
+fn filter_attrs(handle: &Handle, tag: &str) {
+    if let NodeData::Element { attrs, .. } = &handle.data {
+        let allowed = allowed_attrs(tag);
+        attrs.borrow_mut().retain(|attr| {
+            let name = attr.name.local.as_ref();
+            if !allowed.contains(&name) {
+                return false;
+            }
+            if (name == "href" || name == "src") && !is_safe_url(&attr.value) {
+                return false;
+            }
+            if name == "style" && !is_safe_style_value(&attr.value) {
+                return false;
+            }
+            true
+        });
+    }
+}
+
+// Walks `handle`'s children bottom-up, dropping any element whose tag isn't
+// in `ALLOWED_TAGS` while splicing its own children into its place so the
+// surrounding text survives. `OPAQUE_TAGS` elements are dropped together
+// with their children.
+fn clean_children(handle: &Handle) {
+    let old_children: Vec<Handle> = handle.children.borrow().clone();
+    let mut new_children: Vec<Handle> = Vec::with_capacity(old_children.len());
+
+    for child in old_children {
+        clean_children(&child);
+
+        match &child.data {
+            NodeData::Element { name, .. } => {
+                let tag = name.local.as_ref();
+                if ALLOWED_TAGS.contains(&tag) && (tag != "input" || is_checkbox_input(&child)) {
+                    filter_attrs(&child, tag);
+                    new_children.push(child.clone());
+                } else if !OPAQUE_TAGS.contains(&tag) {
+                    // Drain rather than clone: `child` is about to be dropped once this
+                    // loop iteration ends, and `Node`'s `Drop` impl walks its surviving
+                    // children and clears their `children` field too - draining leaves
+                    // `child` with nothing for that walk to clobber once these nodes
+                    // are re-parented below.
+                    new_children.extend(child.children.borrow_mut().drain(..));
+                }
+            }
+            _ => new_children.push(child.clone()),
+        }
+    }
+
+    for child in &new_children {
+        child.parent.set(Some(Rc::downgrade(handle)));
+    }
+    *handle.children.borrow_mut() = new_children;
+}
+
+// DOM-based allowlist sanitizer: parses the rendered HTML as a fragment,
+// strips every element not in `ALLOWED_TAGS` (keeping its text, except for
+// `OPAQUE_TAGS`, whose content is dropped too), drops
+// attributes outside each tag's allowlist, and rejects unsafe `href`/`src`
+// schemes before re-serializing the cleaned tree.
 fn sanitize_html(html: &str) -> String {
-    // GitHub-style HTML sanitization - allow safe HTML tags but escape dangerous ones
-    // This allows HTML within Markdown to be rendered, like GitHub does
-    let mut result = html.to_string();
-
-    // Escape dangerous tags that could execute code or load external content
-    let dangerous_tags = ["script", "iframe", "object", "embed", "form", "meta", "link", "style"];
-    for tag in &dangerous_tags {
-        let open_pattern = format!("<{}", tag);
-        let close_pattern = format!("</{}", tag);
-        result = result.replace(&open_pattern, &format!("&lt;{}", tag));
-        result = result.replace(&close_pattern, &format!("&lt;/{}", tag));
-    }
-
-    // Escape dangerous attributes that could execute JavaScript
-    // More careful replacement to avoid breaking tag names
-    result = result.replace("javascript:", "javascript&colon;");
-    result = result.replace("vbscript:", "vbscript&colon;");
-    result = result.replace("data:", "data&colon;");
-
-    // Escape event handlers more carefully - look for attribute patterns
-    result = result.replace(" onclick", " on&click");
-    result = result.replace(" onload", " on&load");
-    result = result.replace(" onmouseover", " on&mouseover");
-    result = result.replace(" onmouseout", " on&mouseout");
-    result = result.replace(" onkeydown", " on&keydown");
-    result = result.replace(" onkeyup", " on&keyup");
-    result = result.replace(" onsubmit", " on&submit");
-
-    // Handle input tags specially - only allow disabled checkboxes from task lists
-    result = result.replace("<input", "&lt;input");
-    // But allow back disabled checkboxes from task lists
-    result = result.replace("&lt;input disabled", "<input disabled");
-    result = result.replace("&lt;input type=\"checkbox\" disabled", "<input type=\"checkbox\" disabled");
-
-    result
+    let context_name = QualName::new(None, ns!(html), local_name!("body"));
+    let dom = parse_fragment(RcDom::default(), Default::default(), context_name, vec![])
+        .from_utf8()
+        .read_from(&mut html.as_bytes())
+        .expect("parsing an HTML fragment cannot fail");
+
+    clean_children(&dom.document);
+
+    let document: SerializableHandle = dom.document.clone().into();
+    let mut buf = Vec::new();
+    serialize(
+        &mut buf,
+        &document,
+        SerializeOpts {
+            traversal_scope: TraversalScope::ChildrenOnly(None),
+            ..Default::default()
+        },
+    )
+    .expect("serializing to an in-memory buffer cannot fail");
+
+    String::from_utf8(buf).expect("html5ever always serializes valid UTF-8")
+}
+
+// Escapes text-node content. Quotes/apostrophes are left alone - they only
+// need escaping inside attribute values, and this is never used for one.
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape_html_basic(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+// Resolves `code_theme` against syntect's bundled theme set, falling back to
+// "InspiredGitHub" (the same default mdBook uses) when the name is unknown.
+fn resolve_code_theme<'a>(theme_set: &'a ThemeSet, code_theme: &str) -> &'a SyntectTheme {
+    theme_set
+        .themes
+        .get(code_theme)
+        .unwrap_or(&theme_set.themes["InspiredGitHub"])
+}
+
+// Runs syntect's `HighlightLines` over `code` for the (lowercased) fence
+// language `lang`, emitting inline-styled `<span>`s so the output needs no
+// supporting CSS. Returns `None` for an empty or unrecognized language, in
+// which case the caller falls back to plain (still HTML-escaped) output.
+fn highlight_code(
+    code: &str,
+    lang: &str,
+    syntax_set: &SyntaxSet,
+    theme: &SyntectTheme,
+) -> Option<String> {
+    let lang = lang.trim().to_lowercase();
+    if lang.is_empty() {
+        return None;
+    }
+    let syntax = syntax_set.find_syntax_by_token(&lang)?;
+
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let mut out = String::with_capacity(code.len() * 2);
+    for line in LinesWithEndings::from(code) {
+        let ranges = highlighter.highlight_line(line, syntax_set).ok()?;
+        for (style, text) in ranges {
+            if text.is_empty() {
+                continue;
+            }
+            let Color { r, g, b, .. } = style.foreground;
+            out.push_str(&format!(
+                r##"<span style="color:#{r:02x}{g:02x}{b:02x}">{}</span>"##,
+                escape_html(text)
+            ));
+        }
+    }
+    Some(out)
+}
+
+// Scans rendered markdown HTML for pulldown-cmark's `<pre><code
+// class="language-X">...</code></pre>` fenced-code output, highlights the
+// (unescaped) code by its fence language via syntect, and re-escapes it.
+// Blocks with no/unrecognized language pass through untouched (still
+// plain-escaped, as pulldown-cmark left them).
+fn apply_syntax_highlighting(html: &str, code_theme: &str) -> String {
+    const OPEN: &str = "<pre><code";
+    const CLOSE: &str = "</code></pre>";
+
+    let syntax_set = SyntaxSet::load_defaults_newlines();
+    let theme_set = ThemeSet::load_defaults();
+    let theme = resolve_code_theme(&theme_set, code_theme);
+
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find(OPEN) {
+        out.push_str(&rest[..start]);
+        let after_open = &rest[start..];
+
+        // Search for the `>` starting after `OPEN` itself, since `<pre>`'s own
+        // closing `>` would otherwise be matched instead of `<code ...>`'s.
+        let Some(tag_end) = after_open[OPEN.len()..].find('>').map(|i| i + OPEN.len() + 1) else {
+            out.push_str(after_open);
+            rest = "";
+            break;
+        };
+        let open_tag = &after_open[..tag_end];
+        let lang = open_tag
+            .find("class=\"language-")
+            .map(|i| {
+                let after = &open_tag[i + "class=\"language-".len()..];
+                let end = after.find('"').unwrap_or(after.len());
+                &after[..end]
+            })
+            .unwrap_or("");
+
+        let body_and_tail = &after_open[tag_end..];
+        let Some(close) = body_and_tail.find(CLOSE) else {
+            out.push_str(open_tag);
+            rest = body_and_tail;
+            continue;
+        };
+        let escaped_code = &body_and_tail[..close];
+
+        out.push_str(open_tag);
+        match highlight_code(&unescape_html_basic(escaped_code), lang, &syntax_set, theme) {
+            Some(highlighted) => out.push_str(&highlighted),
+            None => out.push_str(escaped_code),
+        }
+        out.push_str(CLOSE);
+
+        rest = &body_and_tail[close + CLOSE.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// RGB values for the named colors `validate_color` accepts, so an
+// `accent_gradient` stop can be a CSS color name as well as a hex code.
+fn named_color_rgb(name: &str) -> Option<(u8, u8, u8)> {
+    Some(match name {
+        "red" => (255, 0, 0),
+        "orange" => (255, 165, 0),
+        "yellow" => (255, 255, 0),
+        "green" => (0, 128, 0),
+        "blue" => (0, 0, 255),
+        "purple" => (128, 0, 128),
+        "pink" => (255, 192, 203),
+        "brown" => (165, 42, 42),
+        "black" => (0, 0, 0),
+        "white" => (255, 255, 255),
+        "gray" | "grey" => (128, 128, 128),
+        "cyan" | "aqua" => (0, 255, 255),
+        "magenta" | "fuchsia" => (255, 0, 255),
+        "lime" => (0, 255, 0),
+        "navy" => (0, 0, 128),
+        "teal" => (0, 128, 128),
+        "maroon" => (128, 0, 0),
+        "olive" => (128, 128, 0),
+        "silver" => (192, 192, 192),
+        "indigo" => (75, 0, 130),
+        "violet" => (238, 130, 238),
+        "gold" => (255, 215, 0),
+        "coral" => (255, 127, 80),
+        "salmon" => (250, 128, 114),
+        "crimson" => (220, 20, 60),
+        "tomato" => (255, 99, 71),
+        _ => return None,
+    })
+}
+
+// Parses any color string `validate_color` accepts (hex or named) into 8-bit
+// sRGB channels. 4/8-digit hex carries an alpha channel, which is dropped -
+// gradient stops have no notion of transparency.
+fn parse_color_to_rgb(color: &str) -> Option<(u8, u8, u8)> {
+    if let Some(hex) = color.strip_prefix('#') {
+        let expand = |c: char| u8::from_str_radix(&format!("{c}{c}"), 16).ok();
+        let pair = |s: &str| u8::from_str_radix(s, 16).ok();
+        return match hex.len() {
+            3 | 4 => {
+                let mut chars = hex.chars();
+                Some((expand(chars.next()?)?, expand(chars.next()?)?, expand(chars.next()?)?))
+            }
+            6 | 8 => Some((pair(&hex[0..2])?, pair(&hex[2..4])?, pair(&hex[4..6])?)),
+            _ => None,
+        };
+    }
+    named_color_rgb(&color.to_lowercase())
+}
+
+fn srgb_to_linear(channel: u8) -> f64 {
+    let c = channel as f64 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(channel: f64) -> u8 {
+    let c = channel.clamp(0.0, 1.0);
+    let s = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (s * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+// Clamped (open) uniform knot vector for `degree` over `num_points` control
+// points: the first/last `degree + 1` knots pin the curve to its first/last
+// control point, with the rest evenly spaced in between.
+fn clamped_knots(num_points: usize, degree: usize) -> Vec<f64> {
+    let num_knots = num_points + degree + 1;
+    let num_internal = num_knots - 2 * (degree + 1);
+    let mut knots = vec![0.0; num_knots];
+    for i in 0..num_internal {
+        knots[degree + 1 + i] = (i + 1) as f64 / (num_internal + 1) as f64;
+    }
+    for knot in knots.iter_mut().rev().take(degree + 1) {
+        *knot = 1.0;
+    }
+    knots
+}
+
+// De Boor's algorithm: evaluates the clamped B-spline of the given `degree`
+// through `control_points` at parameter `t` in [0, 1].
+fn de_boor(degree: usize, knots: &[f64], control_points: &[[f64; 3]], t: f64) -> [f64; 3] {
+    let n = control_points.len() - 1;
+    let mut span = degree;
+    for i in degree..=n {
+        if t >= knots[i] && t < knots[i + 1] {
+            span = i;
+        }
+    }
+    if t >= knots[n + 1] {
+        span = n;
+    }
+
+    let mut d: Vec<[f64; 3]> = (0..=degree).map(|j| control_points[j + span - degree]).collect();
+    for r in 1..=degree {
+        for j in (r..=degree).rev() {
+            let i = j + span - degree;
+            let denom = knots[i + degree - r + 1] - knots[i];
+            let alpha = if denom.abs() < f64::EPSILON { 0.0 } else { (t - knots[i]) / denom };
+            d[j] = [
+                d[j - 1][0] + alpha * (d[j][0] - d[j - 1][0]),
+                d[j - 1][1] + alpha * (d[j][1] - d[j - 1][1]),
+                d[j - 1][2] + alpha * (d[j][2] - d[j - 1][2]),
+            ];
+        }
+    }
+    d[degree]
 }
 
-pub fn generate_html(markdown: &str, font_size: &str, font: &str, theme: &str, accent: &str, accent_light: Option<&str>, accent_dark: Option<&str>, favicon: Option<&str>) -> String {
+// Fits a clamped, uniform B-spline over `stops` (converted to linear-light RGB
+// so interpolation doesn't wash out through sRGB's gamma curve) and samples it
+// at `count` evenly spaced points, one per heading on the page. A single stop
+// degenerates to that solid color repeated; fewer stops than a cubic needs
+// lowers the degree to `stops.len() - 1` instead of erroring.
+fn gradient_colors(stops: &[(u8, u8, u8)], count: usize) -> Vec<(u8, u8, u8)> {
+    if count == 0 {
+        return Vec::new();
+    }
+    if stops.len() <= 1 {
+        let color = stops.first().copied().unwrap_or((0, 0, 0));
+        return vec![color; count];
+    }
+
+    let control_points: Vec<[f64; 3]> = stops
+        .iter()
+        .map(|&(r, g, b)| [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b)])
+        .collect();
+    let degree = (stops.len() - 1).min(3);
+    let knots = clamped_knots(control_points.len(), degree);
+
+    (0..count)
+        .map(|i| {
+            let t = if count == 1 { 0.0 } else { i as f64 / (count - 1) as f64 };
+            let [r, g, b] = de_boor(degree, &knots, &control_points, t);
+            (linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b))
+        })
+        .collect()
+}
+
+// Counts every `<h1>`-`<h6>` opening tag so the gradient can be sampled at
+// exactly one point per heading before `add_heading_anchors` walks them.
+fn count_headings(html: &str) -> usize {
+    (1..=6u8).map(|level| html.matches(&format!("<h{level}>")).count()).sum()
+}
+
+// Strips tags from inline-formatted heading text (e.g. `Hello <em>World</em>`)
+// so the slug is derived from the plain text a reader sees, not the markup.
+fn strip_tags(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut in_tag = false;
+    for c in s.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
+
+// Lowercases, turns runs of characters other than ASCII letters/digits into
+// single hyphens, and trims leading/trailing hyphens. Non-ASCII letters count
+// as separators, so "Héllo" becomes "h-llo".
+fn slugify(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut pending_hyphen = false;
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            if pending_hyphen && !out.is_empty() {
+                out.push('-');
+            }
+            pending_hyphen = false;
+            out.push(c.to_ascii_lowercase());
+        } else {
+            pending_hyphen = true;
+        }
+    }
+    out
+}
+
+/// One heading discovered by `add_heading_anchors`: its level (1-6), the slug
+/// `id` it was given, and its plain display text - enough to render a TOC entry.
+struct Heading {
+    level: u8,
+    id: String,
+    text: String,
+}
+
+// Gives every `<h1>`-`<h6>` a URL-safe `id` slug (de-duplicated by appending
+// `-1`, `-2`, ... on collision) and a clickable `#` anchor that appears on
+// hover, so generated pages are linkable from outside. Also returns the
+// heading list in document order, for `build_toc` to turn into a sidebar.
+// `heading_colors`, when given, assigns `heading_colors[i]` as an inline
+// `color` on the i-th heading encountered - this is how the `accent_gradient`
+// palette (see `gradient_colors`) reaches the page.
+fn add_heading_anchors(html: &str, heading_colors: Option<&[(u8, u8, u8)]>) -> (String, Vec<Heading>) {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    let mut seen: HashMap<String, u32> = HashMap::new();
+    let mut headings = Vec::new();
+    let mut index = 0usize;
+
+    while let Some((start, level)) = (1..=6u8)
+        .filter_map(|level| rest.find(&format!("<h{level}>")).map(|idx| (idx, level)))
+        .min_by_key(|(idx, _)| *idx)
+    {
+        out.push_str(&rest[..start]);
+        let open_tag_len = format!("<h{level}>").len();
+        let after_open = &rest[start + open_tag_len..];
+
+        let close_tag = format!("</h{level}>");
+        let Some(close) = after_open.find(&close_tag) else {
+            out.push_str(&format!("<h{level}>"));
+            rest = after_open;
+            continue;
+        };
+        let inner = &after_open[..close];
+
+        let text = strip_tags(&unescape_html_basic(inner));
+        let mut slug = slugify(&text);
+        if slug.is_empty() {
+            slug = "section".to_string();
+        }
+        let count = seen.entry(slug.clone()).or_insert(0);
+        let id = if *count == 0 { slug.clone() } else { format!("{slug}-{count}") };
+        *count += 1;
+
+        let style_attr = match heading_colors.and_then(|colors| colors.get(index)) {
+            Some((r, g, b)) => format!(" style=\"color:#{r:02x}{g:02x}{b:02x}\""),
+            None => String::new(),
+        };
+
+        out.push_str(&format!(
+            r##"<h{level} id="{id}"{style_attr}>{inner}<a class="heading-anchor" href="#{id}" aria-label="Link to this section">#</a></h{level}>"##
+        ));
+        headings.push(Heading { level, id, text });
+        index += 1;
+
+        rest = &after_open[close + close_tag.len()..];
+    }
+    out.push_str(rest);
+    (out, headings)
+}
+
+// Builds a nested `<ul>` reflecting the h1-h6 hierarchy, linking each entry to
+// its heading's anchor id. Returns an empty string when there are no headings.
+fn build_toc(headings: &[Heading]) -> String {
+    if headings.is_empty() {
+        return String::new();
+    }
+
+    let mut out = String::new();
+    let mut stack: Vec<u8> = Vec::new();
+
+    for heading in headings {
+        while let Some(&top) = stack.last() {
+            if heading.level < top {
+                out.push_str("</li></ul>");
+                stack.pop();
+            } else {
+                break;
+            }
+        }
+        match stack.last() {
+            Some(&top) if heading.level == top => out.push_str("</li>"),
+            _ => {
+                out.push_str("<ul>");
+                stack.push(heading.level);
+            }
+        }
+        out.push_str(&format!(
+            r##"<li><a href="#{}">{}</a>"##,
+            heading.id,
+            escape_html(&heading.text)
+        ));
+    }
+    for _ in &stack {
+        out.push_str("</li></ul>");
+    }
+    out
+}
+
+/// A user-defined theme: a name shown in the theme picker plus the CSS
+/// custom-property overrides (e.g. `--bg-color`, `--link-color`) it applies.
+pub struct CustomTheme {
+    pub name: String,
+    pub vars: Vec<(String, String)>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn generate_html(markdown: &str, font_size: &str, font: &str, theme: &str, accent: &str, accent_light: Option<&str>, accent_dark: Option<&str>, favicon: Option<&str>, custom_themes: &[CustomTheme], head_html: Option<&str>, extension_css: Option<&str>, code_theme: &str, accent_gradient: Option<&[String]>) -> String {
     // Generate favicon link from emoji if provided
     let favicon_link = if let Some(emoji) = favicon {
         format!(r#"<link rel="icon" href="data:image/svg+xml,<svg xmlns=%22http://www.w3.org/2000/svg%22 viewBox=%220 0 100 100%22><text y=%22.9em%22 font-size=%2290%22>{}</text></svg>">"#, 
@@ -61,8 +613,39 @@ pub fn generate_html(markdown: &str, font_size: &str, font: &str, theme: &str, a
     let mut html_output = String::new();
     html::push_html(&mut html_output, parser);
 
+    // Tokenize fenced code blocks by their fence language before sanitizing,
+    // so the injected `<span style="color:#rrggbb">` tokens are present for the
+    // `span` attribute allowlist to preserve.
+    let highlighted_output = apply_syntax_highlighting(&html_output, code_theme);
+
+    // A single stop (or none) degenerates to the solid `accent` color already
+    // baked into the CSS custom properties below, so no per-heading colors.
+    let gradient_stops: Vec<(u8, u8, u8)> = accent_gradient
+        .map(|stops| stops.iter().filter_map(|s| parse_color_to_rgb(s)).collect())
+        .unwrap_or_default();
+    let heading_colors = if gradient_stops.len() > 1 {
+        Some(gradient_colors(&gradient_stops, count_headings(&highlighted_output)))
+    } else {
+        None
+    };
+
+    // Give every heading a stable, linkable id before sanitizing.
+    let (anchored_output, headings) = add_heading_anchors(&highlighted_output, heading_colors.as_deref());
+    let toc_list = build_toc(&headings);
+    let toc_nav = if toc_list.is_empty() {
+        String::new()
+    } else {
+        format!(
+            r#"<nav class="toc" id="statgen-toc">
+        <button class="toc-toggle" onclick="toggleToc()" aria-label="Toggle table of contents">&#9776; Contents</button>
+        <div class="toc-list">{toc_list}</div>
+    </nav>
+    "#
+        )
+    };
+
     // Sanitize the HTML output to remove dangerous tags
-    let sanitized_output = sanitize_html(&html_output);
+    let sanitized_output = sanitize_html(&anchored_output);
 
     // Extract title from markdown or use default
     let title = extract_title(markdown).unwrap_or_else(|| "Static Site".to_string());
@@ -74,49 +657,123 @@ pub fn generate_html(markdown: &str, font_size: &str, font: &str, theme: &str, a
         _ => ("#f4f4f4", "#333", "#2c3e50", "#e7e7e7", "#333", "#f9f9f9", "#e0e0e0"), // Light theme
     };
 
-    let theme_script = if theme == "auto" {
-        let light_accent = accent_light.as_deref().unwrap_or(accent);
-        let dark_accent = accent_dark.as_deref().unwrap_or(accent);
-        
-        format!(r#"<script>
+    // Theme-switching JavaScript is emitted for every mode, not just "auto": the
+    // persisted localStorage choice (set by the picker below) must be able to win
+    // over whatever theme was baked in at generation time. Only when the stored
+    // preference is "auto" does the matchMedia listener get to drive it.
+    //
+    // Every theme (built-in light/dark and any custom theme) is expressed as a
+    // `:root[data-theme="name"]` CSS rule, so switching is just a matter of
+    // setting that attribute - no per-property JS needed.
+    let light_accent = accent_light.unwrap_or(accent);
+    let dark_accent = accent_dark.unwrap_or(accent);
+
+    let built_in_theme_rules = format!(
+        r#"
+        :root[data-theme="light"] {{
+            --bg-color: #f4f4f4; --text-color: #333; --header-color: #2c3e50;
+            --code-bg: #e7e7e7; --code-color: #333; --link-color: {light_accent};
+            --blockquote-bg: #f9f9f9; --border-color: #e0e0e0;
+        }}
+
+        :root[data-theme="dark"] {{
+            --bg-color: #1a1a1a; --text-color: #e0e0e0; --header-color: #ffffff;
+            --code-bg: #2d2d2d; --code-color: #cccccc; --link-color: {dark_accent};
+            --blockquote-bg: #2a2a2a; --border-color: #404040;
+        }}"#
+    );
+
+    let custom_theme_rules: String = custom_themes
+        .iter()
+        .map(|t| {
+            let vars: String = t
+                .vars
+                .iter()
+                .map(|(prop, value)| format!("{}: {};", prop, value))
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!(
+                "\n        :root[data-theme=\"{}\"] {{ {} }}",
+                t.name, vars
+            )
+        })
+        .collect();
+
+    let theme_option_tags: String = std::iter::once(("light", "Light"))
+        .chain(std::iter::once(("dark", "Dark")))
+        .chain(std::iter::once(("auto", "Auto")))
+        .map(|(value, label)| format!(r#"<option value="{}">{}</option>"#, value, label))
+        .chain(
+            custom_themes
+                .iter()
+                .map(|t| format!(r#"<option value="{0}">{0}</option>"#, t.name)),
+        )
+        .collect();
+
+    let theme_script = format!(
+        r#"<script>
+        const STATGEN_THEME_KEY = 'statgen-theme';
+
         function applyTheme(theme) {{
             const root = document.documentElement;
-            if (theme === 'dark') {{
-                root.style.setProperty('--bg-color', '#1a1a1a');
-                root.style.setProperty('--text-color', '#e0e0e0');
-                root.style.setProperty('--header-color', '#ffffff');
-                root.style.setProperty('--code-bg', '#2d2d2d');
-                root.style.setProperty('--code-color', '#cccccc');
-                root.style.setProperty('--link-color', '{}');
-                root.style.setProperty('--blockquote-bg', '#2a2a2a');
-                root.style.setProperty('--border-color', '#404040');
-            }} else {{
-                root.style.setProperty('--bg-color', '#f4f4f4');
-                root.style.setProperty('--text-color', '#333');
-                root.style.setProperty('--header-color', '#2c3e50');
-                root.style.setProperty('--code-bg', '#e7e7e7');
-                root.style.setProperty('--code-color', '#333');
-                root.style.setProperty('--link-color', '{}');
-                root.style.setProperty('--blockquote-bg', '#f9f9f9');
-                root.style.setProperty('--border-color', '#e0e0e0');
+            const resolved = theme === 'auto'
+                ? (window.matchMedia('(prefers-color-scheme: dark)').matches ? 'dark' : 'light')
+                : theme;
+            root.setAttribute('data-theme', resolved);
+            const picker = document.getElementById('statgen-theme-picker');
+            if (picker) {{
+                picker.value = theme;
             }}
         }}
 
-        // Detect system theme
-        const prefersDark = window.matchMedia('(prefers-color-scheme: dark)').matches;
-        applyTheme(prefersDark ? 'dark' : 'light');
+        function setTheme(theme) {{
+            localStorage.setItem(STATGEN_THEME_KEY, theme);
+            applyTheme(theme);
+        }}
+
+        function toggleToc() {{
+            const toc = document.getElementById('statgen-toc');
+            if (toc) {{
+                toc.classList.toggle('toc-open');
+            }}
+        }}
 
-        // Listen for changes
-        window.matchMedia('(prefers-color-scheme: dark)').addEventListener('change', (e) => {{
-            applyTheme(e.matches ? 'dark' : 'light');
+        // This script runs in <head>, before <body> (and the toggle/picker
+        // element it syncs) has been parsed, so `getElementById` would miss it -
+        // defer the initial sync until the DOM is ready. The persisted choice
+        // wins over the theme baked in at generation time.
+        document.addEventListener('DOMContentLoaded', function () {{
+            applyTheme(localStorage.getItem(STATGEN_THEME_KEY) || '{initial_theme}');
         }});
-        </script>"#, dark_accent, light_accent)
-    } else {
-        String::new()
-    };
+
+        // Only re-derive from the OS preference when the stored choice is "auto".
+        window.matchMedia('(prefers-color-scheme: dark)').addEventListener('change', () => {{
+            if ((localStorage.getItem(STATGEN_THEME_KEY) || '{initial_theme}') === 'auto') {{
+                applyTheme('auto');
+            }}
+        }});
+        </script>"#,
+        initial_theme = theme,
+    );
+
+    let theme_picker = format!(
+        r#"<select id="statgen-theme-picker" class="theme-picker" onchange="setTheme(this.value)" aria-label="Choose color theme">{}</select>"#,
+        theme_option_tags
+    );
 
     let css_variables = format!("--bg-color: {}; --text-color: {}; --header-color: {}; --code-bg: {}; --code-color: {}; --link-color: {}; --blockquote-bg: {}; --border-color: {};", body_bg, body_color, header_color, code_bg, code_color, accent, blockquote_bg, border_color);
 
+    // Emitted after the built-in `<style>` block so its rules win ties on
+    // specificity, letting users override defaults without forking the generator.
+    let extension_style = match extension_css {
+        Some(css) if !css.is_empty() => format!("<style>\n{}\n</style>", css),
+        _ => String::new(),
+    };
+
+    // Arbitrary head content (analytics snippets, meta tags, web-font links, ...)
+    // spliced in right before `</head>`, mirroring rustdoc's `ExternalHtml::in_header`.
+    let head_extra = head_html.unwrap_or("");
+
     format!(
         r#"<!DOCTYPE html>
 <html lang="en">
@@ -130,6 +787,8 @@ pub fn generate_html(markdown: &str, font_size: &str, font: &str, theme: &str, a
             {};
             --shadow: 0 2px 8px rgba(0, 0, 0, 0.1);
         }}
+        {}
+        {}
 
         * {{
             box-sizing: border-box;
@@ -160,6 +819,53 @@ pub fn generate_html(markdown: &str, font_size: &str, font: &str, theme: &str, a
         .content {{
             text-align: left;
             width: 100%;
+            min-width: 0;
+        }}
+
+        .toc {{
+            flex: 0 0 220px;
+            width: 220px;
+            margin-right: 2rem;
+        }}
+
+        .toc-toggle {{
+            display: none;
+        }}
+
+        .toc-list {{
+            position: sticky;
+            top: 2rem;
+            max-height: calc(100vh - 4rem);
+            overflow-y: auto;
+            background-color: var(--bg-color);
+            border: 1px solid var(--border-color);
+            border-radius: 4px;
+            padding: 1rem;
+        }}
+
+        .toc-list ul {{
+            list-style: none;
+            padding-left: 1rem;
+            margin: 0;
+        }}
+
+        .toc-list > ul {{
+            padding-left: 0;
+        }}
+
+        .toc-list li {{
+            margin: 0.4rem 0;
+        }}
+
+        .toc-list a {{
+            color: var(--text-color);
+            text-decoration: none;
+            font-size: 0.9rem;
+        }}
+
+        .toc-list a:hover {{
+            color: var(--link-color);
+            text-decoration: underline;
         }}
 
         h1, h2, h3, h4, h5, h6 {{
@@ -204,6 +910,23 @@ pub fn generate_html(markdown: &str, font_size: &str, font: &str, theme: &str, a
             color: var(--code-color);
         }}
 
+        .heading-anchor {{
+            margin-left: 0.5rem;
+            color: var(--link-color);
+            text-decoration: none;
+            font-weight: 400;
+            opacity: 0;
+        }}
+
+        h1:hover .heading-anchor,
+        h2:hover .heading-anchor,
+        h3:hover .heading-anchor,
+        h4:hover .heading-anchor,
+        h5:hover .heading-anchor,
+        h6:hover .heading-anchor {{
+            opacity: 1;
+        }}
+
         p {{
             margin-bottom: 2rem;
             text-align: left;
@@ -314,10 +1037,56 @@ pub fn generate_html(markdown: &str, font_size: &str, font: &str, theme: &str, a
             color: var(--code-color);
         }}
 
+        .theme-picker {{
+            position: fixed;
+            top: 1rem;
+            right: 1rem;
+            padding: 0.5rem 1rem;
+            background-color: var(--bg-color);
+            color: var(--text-color);
+            border: 1px solid var(--border-color);
+            border-radius: 6px;
+            font-size: 0.85rem;
+            cursor: pointer;
+            box-shadow: var(--shadow);
+        }}
+
+        .theme-picker:hover {{
+            color: var(--link-color);
+        }}
+
         /* Responsive design */
         @media (max-width: 768px) {{
             .container {{
                 padding: 1.5rem 1rem;
+                flex-direction: column;
+            }}
+
+            .toc {{
+                width: 100%;
+                margin-right: 0;
+                margin-bottom: 1.5rem;
+            }}
+
+            .toc-toggle {{
+                display: block;
+                width: 100%;
+                margin-bottom: 0.5rem;
+                padding: 0.6rem 1rem;
+                background-color: var(--bg-color);
+                color: var(--text-color);
+                border: 1px solid var(--border-color);
+                border-radius: 6px;
+                font-size: 0.9rem;
+                cursor: pointer;
+            }}
+
+            .toc-list {{
+                display: none;
+            }}
+
+            .toc.toc-open .toc-list {{
+                display: block;
             }}
 
             h1 {{
@@ -346,16 +1115,31 @@ pub fn generate_html(markdown: &str, font_size: &str, font: &str, theme: &str, a
         }}
     </style>
     {}
+    {}
+    {}
 </head>
 <body>
+    {}
     <div class="container">
-        <div class="content">
+        {}<div class="content">
             {}
         </div>
     </div>
 </body>
 </html>"#,
-        title, favicon_link, css_variables, font, font_size, theme_script, sanitized_output
+        title,
+        favicon_link,
+        css_variables,
+        built_in_theme_rules,
+        custom_theme_rules,
+        font,
+        font_size,
+        extension_style,
+        theme_script,
+        head_extra,
+        theme_picker,
+        toc_nav,
+        sanitized_output,
     )
 }
 
@@ -438,22 +1222,22 @@ mod tests {
     #[test]
     fn test_basic_markdown_parsing() {
         let markdown = "# Hello World\n\nThis is **bold** text.";
-        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None);
-        assert!(html.contains("<h1>Hello World</h1>"));
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
+        assert!(html.contains(r#"<h1 id="hello-world">Hello World"#));
         assert!(html.contains("<strong>bold</strong>"));
     }
 
     #[test]
     fn test_image_parsing() {
         let markdown = "![test image](https://example.com/image.jpg)";
-        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         assert!(html.contains("<img src=\"https://example.com/image.jpg\" alt=\"test image\""));
     }
 
     #[test]
     fn test_link_parsing() {
         let markdown = "[link text](https://example.com)";
-        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         assert!(html.contains("<a href=\"https://example.com\">link text</a>"));
     }
 
@@ -461,7 +1245,7 @@ mod tests {
     fn test_footnotes() {
         // Footnotes not supported in current pulldown-cmark version
         let markdown = "Text with footnote[^1]\n\n[^1]: Footnote content";
-        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         // Footnotes render as plain text
         assert!(html.contains("footnote"));
         assert!(html.contains("Footnote content"));
@@ -471,7 +1255,7 @@ mod tests {
     fn test_strikethrough() {
         // Strikethrough not supported in current pulldown-cmark version
         let markdown = "~~strikethrough text~~";
-        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         // Strikethrough renders as plain text
         assert!(html.contains("strikethrough text"));
     }
@@ -480,7 +1264,7 @@ mod tests {
     fn test_task_lists() {
         // Task lists not supported in current pulldown-cmark version
         let markdown = "- [ ] Incomplete\n- [x] Complete";
-        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         // Task lists render as plain text
         assert!(html.contains("Incomplete"));
         assert!(html.contains("Complete"));
@@ -489,9 +1273,74 @@ mod tests {
     #[test]
     fn test_code_block_parsing() {
         let markdown = "```rust\nfn main() {}\n```";
-        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         assert!(html.contains("<pre><code class=\"language-rust\">"));
-        assert!(html.contains("fn main() {}"));
+        // Recognized languages are highlighted via syntect, which wraps each
+        // token in an inline-styled span rather than leaving it as plain text.
+        assert!(html.contains(r#"<span style="color:#"#));
+    }
+
+    #[test]
+    fn test_heading_anchors_are_slugified_and_deduplicated() {
+        let markdown = "# Getting Started!\n\n## Getting Started!\n\n### Héllo Wörld";
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
+        assert!(html.contains(r#"<h1 id="getting-started">Getting Started!"#));
+        // A second heading that slugifies to the same id gets a numeric suffix.
+        assert!(html.contains(r#"<h2 id="getting-started-1">Getting Started!"#));
+        assert!(html.contains(r#"<h3 id="h-llo-w-rld">Héllo Wörld"#));
+        // Each heading carries a hover-revealed anchor link back to its own id.
+        assert!(html.contains(r##"<a class="heading-anchor" href="#getting-started" aria-label="Link to this section">#</a>"##));
+    }
+
+    #[test]
+    fn test_toc_reflects_heading_hierarchy() {
+        let markdown = "# Title\n\n## Section One\n\n### Sub One\n\n## Section Two";
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
+        assert!(html.contains(r#"<nav class="toc" id="statgen-toc">"#));
+        assert!(html.contains(r##"<a href="#title">Title</a>"##));
+        assert!(html.contains(r##"<a href="#section-one">Section One</a>"##));
+        // Sub One nests in a new <ul> inside Section One's <li>, which is closed
+        // before Section Two's sibling <li> opens.
+        assert!(html.contains(r##"<li><a href="#section-one">Section One</a><ul><li><a href="#sub-one">Sub One</a></li></ul></li><li><a href="#section-two">Section Two</a>"##));
+        let sub_idx = html.find(r##"<a href="#sub-one">Sub One</a>"##).unwrap();
+        let section_two_idx = html.find(r##"<a href="#section-two">Section Two</a>"##).unwrap();
+        assert!(sub_idx < section_two_idx);
+    }
+
+    #[test]
+    fn test_toc_absent_when_no_headings() {
+        let markdown = "Just a paragraph, no headings here.";
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
+        // The `toggleToc()` helper always references the id, but the `<nav>` itself
+        // should be omitted entirely when there are no headings to list.
+        assert!(!html.contains(r#"<nav class="toc""#));
+    }
+
+    #[test]
+    fn test_syntax_highlighting_unknown_language_falls_back_to_plain() {
+        let markdown = "```made-up-lang\nsome text here\n```";
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
+        assert!(html.contains("<pre><code class=\"language-made-up-lang\">some text here"));
+        assert!(!html.contains(r#"<span style="color:#"#));
+    }
+
+    #[test]
+    fn test_syntax_highlighting_respects_code_theme() {
+        let markdown = "```python\n# a comment\nx = \"hello\"\n```";
+        let light = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
+        let dark = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "base16-ocean.dark", None);
+        assert!(light.contains(r#"<span style="color:#"#));
+        assert!(dark.contains(r#"<span style="color:#"#));
+        // Different syntect themes assign different colors to the same tokens.
+        assert_ne!(light, dark);
+    }
+
+    #[test]
+    fn test_unknown_code_theme_falls_back_to_default() {
+        let markdown = "```rust\nfn main() {}\n```";
+        let fallback = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "not-a-real-theme", None);
+        let default_theme = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
+        assert_eq!(fallback, default_theme);
     }
 
 
@@ -501,29 +1350,75 @@ mod tests {
         let dangerous_html = "<script>alert('xss')</script><h1>Safe</h1>";
         let sanitized = sanitize_html(dangerous_html);
         assert!(!sanitized.contains("<script"));
-        assert!(sanitized.contains("&lt;script"));
+        assert!(!sanitized.contains("alert"));
         assert!(sanitized.contains("<h1>Safe</h1>"));
     }
 
+    #[test]
+    fn test_sanitization_catches_case_and_split_bypasses() {
+        assert!(!sanitize_html("<SCRIPT>alert(1)</SCRIPT>").contains("alert"));
+        // Malformed/split tags (unterminated or nested mid-name) never form a real
+        // `<script>` element, so the payload survives only as inert escaped text -
+        // exactly like a real browser would render it, and thus cannot execute.
+        assert!(!sanitize_html("< script>alert(1)</script>").to_lowercase().contains("<script"));
+        assert!(!sanitize_html("<scr<script>ipt>alert(1)</script>").to_lowercase().contains("<script"));
+        assert!(!sanitize_html("<img src=x onerror=\"alert(1)\">").contains("onerror"));
+        assert!(!sanitize_html("<img src=x onError=\"alert(1)\">").to_lowercase().contains("onerror"));
+    }
+
+    #[test]
+    fn test_sanitization_keeps_only_checkbox_inputs() {
+        assert!(sanitize_html("<input type=\"checkbox\" disabled>").contains("<input"));
+        assert!(!sanitize_html("<input type=\"text\">").contains("<input"));
+        assert!(!sanitize_html("<input type=\"radio\">").contains("<input"));
+        assert!(!sanitize_html("<input type=\"file\">").contains("<input"));
+        assert!(!sanitize_html("<input>").contains("<input"));
+    }
+
+    #[test]
+    fn test_sanitization_restricts_heading_style_to_generated_color_shape() {
+        assert!(sanitize_html("<h1 style=\"color:#ff0000\">Safe</h1>").contains(r#"style="color:#ff0000""#));
+        assert!(!sanitize_html("<h1 style=\"background:url(https://evil.example/beacon)\">x</h1>").contains("style"));
+        assert!(!sanitize_html("<h1 style=\"position:fixed;z-index:9999\">x</h1>").contains("style"));
+        assert!(!sanitize_html("<h1 style=\"color:#FF0000\">x</h1>").contains("style"));
+    }
+
+    #[test]
+    fn test_sanitization_restricts_span_style_to_generated_color_shape() {
+        assert!(sanitize_html("<span style=\"color:#00ff00\">Safe</span>").contains(r#"style="color:#00ff00""#));
+        assert!(!sanitize_html("<span style=\"background:url(https://evil.example/beacon)\">x</span>").contains("style"));
+        assert!(!sanitize_html("<span style=\"color:#FF0000\">x</span>").contains("style"));
+        assert!(!sanitize_html("<span style=\"color:red\">x</span>").contains("style"));
+    }
+
+    #[test]
+    fn test_sanitization_rejects_unsafe_url_schemes() {
+        assert!(!sanitize_html("<a href=\"javascript:alert(1)\">x</a>").contains("href"));
+        assert!(!sanitize_html("<a href=\" JavaScript:alert(1)\">x</a>").contains("href"));
+        assert!(!sanitize_html("<img src=\"data:text/html,evil\">").contains("src"));
+        assert!(sanitize_html("<a href=\"https://example.com\">x</a>").contains("href=\"https://example.com\""));
+        assert!(sanitize_html("<a href=\"/relative/path\">x</a>").contains("href=\"/relative/path\""));
+    }
+
     #[test]
     fn test_xss_in_generate_html() {
         let dangerous_markdown = "<script>alert('xss')</script>\n\n# Safe Header";
-        let html = generate_html(dangerous_markdown, "16px", "sans-serif", "light", "#3498db", None, None, None);
-        assert!(!html.contains("<script"));
-        assert!(html.contains("&lt;script"));
-        assert!(html.contains("<h1>Safe Header</h1>"));
+        let html = generate_html(dangerous_markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
+        // The page always carries its own theme-toggle <script>; the user-supplied one must not survive.
+        assert!(!html.contains("alert"));
+        assert!(html.contains(r#"<h1 id="safe-header">Safe Header"#));
     }
 
     #[test]
     fn test_no_title_extraction() {
         let markdown = "Just content, no header.";
-        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         assert!(html.contains("<title>Static Site</title>"));
     }
 
     #[test]
     fn test_font_size_customization() {
-        let html = generate_html("# Test", "18px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html("# Test", "18px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         assert!(html.contains("font-family: sans-serif"));
         assert!(html.contains("font-size: 18px"));
     }
@@ -540,14 +1435,14 @@ mod tests {
 
     #[test]
     fn test_xss_prevention() {
-        // Test that dangerous HTML/script tags are sanitized/escaped
+        // Test that dangerous HTML/script tags are sanitized away
         let malicious_markdown = "<script>alert('xss')</script>\n\n# Normal Header";
-        let html = generate_html(malicious_markdown, "16px", "sans-serif", "light", "#3498db", None, None, None);
-        // Dangerous script tags should be escaped for security
-        assert!(!html.contains("<script"));
-        assert!(html.contains("&lt;script"));
+        let html = generate_html(malicious_markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
+        // Dangerous script tags (and their contents) should be stripped entirely; only the
+        // page's own theme-toggle <script> remains.
+        assert!(!html.contains("alert"));
         // But safe content should remain
-        assert!(html.contains("<h1>Normal Header</h1>"));
+        assert!(html.contains(r#"<h1 id="normal-header">Normal Header"#));
     }
 
     #[test]
@@ -567,15 +1462,15 @@ mod tests {
     #[test]
     fn test_large_content() {
         let large_markdown = "# Large Content\n\n".repeat(1000);
-        let html = generate_html(&large_markdown, "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html(&large_markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         assert!(html.len() > large_markdown.len());
-        assert!(html.contains("<h1>Large Content</h1>"));
+        assert!(html.contains(r#"<h1 id="large-content">Large Content"#));
     }
 
     #[test]
     fn test_special_characters() {
         let markdown = "# Spëcial Chärs 🚀\n\n**Bôld** and *ïtálic*.";
-        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         assert!(html.contains("Spëcial Chärs 🚀"));
         assert!(html.contains("<strong>Bôld</strong>"));
         assert!(html.contains("<em>ïtálic</em>"));
@@ -583,7 +1478,7 @@ mod tests {
 
     #[test]
     fn test_empty_input() {
-        let html = generate_html("", "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html("", "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         assert!(html.contains("<div class=\"content\">"));
         assert!(html.contains("</div>"));
     }
@@ -592,7 +1487,7 @@ mod tests {
     fn test_table_parsing() {
         // Tables not supported in current pulldown-cmark version
         let markdown = "| Header1 | Header2 |\n|---------|---------|\n| Cell1   | Cell2   |";
-        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         // Tables render as plain text
         assert!(html.contains("Header1"));
         assert!(html.contains("Cell1"));
@@ -602,8 +1497,8 @@ mod tests {
     fn test_newline_unescaping() {
         let escaped = "# Title\\n\\nParagraph\\n\\n- Item 1\\n- Item 2";
         let unescaped = unescape_newlines(escaped);
-        let html = generate_html(&unescaped, "16px", "sans-serif", "light", "#3498db", None, None, None);
-        assert!(html.contains("<h1>Title</h1>"));
+        let html = generate_html(&unescaped, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
+        assert!(html.contains(r#"<h1 id="title">Title"#));
         assert!(html.contains("<p>Paragraph</p>"));
         assert!(html.contains("<li>Item 1</li>"));
         assert!(html.contains("<li>Item 2</li>"));
@@ -611,7 +1506,7 @@ mod tests {
 
     #[test]
     fn test_layout_structure() {
-        let html = generate_html("# Test", "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html("# Test", "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         // Test container structure
         assert!(html.contains("<div class=\"container\">"));
         assert!(html.contains("<div class=\"content\">"));
@@ -628,20 +1523,20 @@ mod tests {
 
     #[test]
     fn test_html_support() {
-        let html = generate_html("# Test\n\n<div class=\"custom\">HTML content</div>\n\n**Markdown** here", "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html("# Test\n\n<div class=\"custom\">HTML content</div>\n\n**Markdown** here", "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         // Test that HTML tags are preserved
         assert!(html.contains("<div class=\"custom\">HTML content</div>"));
         // Test that Markdown is still processed
         assert!(html.contains("<strong>Markdown</strong>"));
-        // Test that dangerous HTML is sanitized
-        let dangerous_html = generate_html("# Test\n\n<script>alert('xss')</script>", "16px", "sans-serif", "light", "#3498db", None, None, None);
-        assert!(!dangerous_html.contains("<script"));
-        assert!(dangerous_html.contains("&lt;script"));
+        // Test that dangerous HTML is sanitized. The page always carries its own
+        // theme-toggle <script>, so only the absence of the injected payload matters.
+        let dangerous_html = generate_html("# Test\n\n<script>alert('xss')</script>", "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
+        assert!(!dangerous_html.contains("alert"));
     }
 
     #[test]
     fn test_responsive_design() {
-        let html = generate_html("# Test", "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html("# Test", "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         // Test responsive media query
         assert!(html.contains("@media (max-width: 768px)"));
         assert!(html.contains("padding: 1.5rem 1rem;"));
@@ -653,23 +1548,24 @@ mod tests {
     #[test]
     fn test_theme_css_variables() {
         // Test light theme
-        let html_light = generate_html("# Test", "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html_light = generate_html("# Test", "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         assert!(html_light.contains("--bg-color: #f4f4f4"));
         assert!(html_light.contains("--text-color: #333"));
         assert!(html_light.contains("--header-color: #2c3e50"));
         assert!(html_light.contains("--link-color: #3498db"));
-        assert!(!html_light.contains("<script>")); // No auto theme script for light
+        // The toggle/persistence script is now emitted for every theme mode.
+        assert!(html_light.contains("function applyTheme(theme)"));
 
         // Test dark theme
-        let html_dark = generate_html("# Test", "16px", "sans-serif", "dark", "#3498db", None, None, None);
+        let html_dark = generate_html("# Test", "16px", "sans-serif", "dark", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         assert!(html_dark.contains("--bg-color: #1a1a1a"));
         assert!(html_dark.contains("--text-color: #e0e0e0"));
         assert!(html_dark.contains("--header-color: #ffffff"));
         assert!(html_dark.contains("--link-color: #3498db"));
-        assert!(!html_dark.contains("<script>")); // No auto theme script for dark
+        assert!(html_dark.contains("function applyTheme(theme)"));
 
         // Test auto theme
-        let html_auto = generate_html("# Test", "16px", "sans-serif", "auto", "#3498db", None, None, None);
+        let html_auto = generate_html("# Test", "16px", "sans-serif", "auto", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         assert!(html_auto.contains("--bg-color: #f4f4f4")); // Default light values
         assert!(html_auto.contains("function applyTheme(theme)"));
         assert!(html_auto.contains("prefers-color-scheme: dark"));
@@ -678,28 +1574,110 @@ mod tests {
 
     #[test]
     fn test_theme_switching_javascript() {
-        let html = generate_html("# Test", "16px", "sans-serif", "auto", "#3498db", None, None, None);
+        let html = generate_html("# Test", "16px", "sans-serif", "auto", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         // Test theme switching function
         assert!(html.contains("function applyTheme(theme)"));
-        assert!(html.contains("root.style.setProperty('--bg-color'"));
-        assert!(html.contains("root.style.setProperty('--text-color'"));
-        assert!(html.contains("root.style.setProperty('--header-color'"));
-        assert!(html.contains("root.style.setProperty('--code-bg'"));
-        assert!(html.contains("root.style.setProperty('--code-color'"));
-        assert!(html.contains("root.style.setProperty('--blockquote-bg'"));
-        assert!(html.contains("root.style.setProperty('--border-color'"));
+        // Theme switching flips the `data-theme` attribute; the CSS custom
+        // properties it selects are defined once per theme, not poked in from JS.
+        assert!(html.contains("root.setAttribute('data-theme'"));
 
         // Test system theme detection
         assert!(html.contains("window.matchMedia('(prefers-color-scheme: dark)').matches"));
-        assert!(html.contains("applyTheme(prefersDark ? 'dark' : 'light')"));
 
-        // Test theme change listener
+        // Test theme change listener is gated on the stored preference being "auto"
         assert!(html.contains("window.matchMedia('(prefers-color-scheme: dark)').addEventListener('change'"));
+        assert!(html.contains("=== 'auto'"));
+    }
+
+    #[test]
+    fn test_theme_toggle_persistence() {
+        let html = generate_html("# Test", "16px", "sans-serif", "dark", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
+        // A visible picker control is injected into <body>.
+        assert!(html.contains(r#"id="statgen-theme-picker""#));
+        assert!(html.contains("onchange=\"setTheme(this.value)\""));
+        // The persisted choice is read before the system-preference fallback runs.
+        assert!(html.contains("localStorage.getItem(STATGEN_THEME_KEY)"));
+        assert!(html.contains("localStorage.setItem(STATGEN_THEME_KEY"));
+        assert!(html.contains("'statgen-theme'"));
+    }
+
+    #[test]
+    fn test_custom_themes_render_as_data_theme_rules() {
+        let themes = vec![CustomTheme {
+            name: "solarized".to_string(),
+            vars: vec![
+                ("--bg-color".to_string(), "#002b36".to_string()),
+                ("--link-color".to_string(), "#268bd2".to_string()),
+            ],
+        }];
+        let html = generate_html("# Test", "16px", "sans-serif", "light", "#3498db", None, None, None, &themes, None, None, "InspiredGitHub", None);
+        assert!(html.contains(r#":root[data-theme="solarized"]"#));
+        assert!(html.contains("--bg-color: #002b36;"));
+        assert!(html.contains("--link-color: #268bd2;"));
+        assert!(html.contains(r#"<option value="solarized">solarized</option>"#));
+    }
+
+    #[test]
+    fn test_accent_gradient_assigns_distinct_heading_colors() {
+        let markdown = "# One\n\n## Two\n\n### Three";
+        let stops = vec!["#ff0000".to_string(), "#0000ff".to_string()];
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", Some(&stops));
+        // The first stop anchors t=0 and the last stop anchors t=1 exactly.
+        assert!(html.contains(r#"<h1 id="one" style="color:#ff0000">"#));
+        assert!(html.contains(r#"<h3 id="three" style="color:#0000ff">"#));
+        // The middle heading gets an interpolated color, distinct from both ends.
+        assert!(!html.contains(r#"<h2 id="two" style="color:#ff0000">"#));
+        assert!(!html.contains(r#"<h2 id="two" style="color:#0000ff">"#));
+    }
+
+    #[test]
+    fn test_accent_gradient_single_stop_is_solid_color() {
+        let markdown = "# One\n\n## Two";
+        let stops = vec!["#ff0000".to_string()];
+        let html = generate_html(markdown, "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", Some(&stops));
+        // A single stop degenerates to the existing solid-accent CSS path rather
+        // than engaging the spline machinery, so no per-heading `style` is added.
+        assert!(!html.contains(r#"<h1 id="one" style="#));
+        assert!(!html.contains(r#"<h2 id="two" style="#));
+    }
+
+    #[test]
+    fn test_no_accent_gradient_by_default() {
+        let html = generate_html("# Test", "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
+        assert!(!html.contains(r#"<h1 id="test" style="#));
+    }
+
+    #[test]
+    fn test_head_html_spliced_before_closing_head_tag() {
+        let head_html = r#"<meta name="description" content="A test site">"#;
+        let html = generate_html("# Test", "16px", "sans-serif", "light", "#3498db", None, None, None, &[], Some(head_html), None, "InspiredGitHub", None);
+        assert!(html.contains(head_html));
+        // It must land inside <head>, not leak into the body.
+        let head_end = html.find("</head>").unwrap();
+        assert!(html.find(head_html).unwrap() < head_end);
+    }
+
+    #[test]
+    fn test_extension_css_appended_after_built_in_style_block() {
+        let extension_css = ".content { max-width: 900px; }";
+        let html = generate_html("# Test", "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, Some(extension_css), "InspiredGitHub", None);
+        assert!(html.contains(extension_css));
+        // It must come after the built-in `<style>` block closes, so it wins ties
+        // on specificity via normal CSS cascade ordering.
+        let built_in_style_end = html.find("</style>").unwrap();
+        assert!(html.find(extension_css).unwrap() > built_in_style_end);
+    }
+
+    #[test]
+    fn test_no_extension_content_by_default() {
+        let html = generate_html("# Test", "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
+        // Absent extension CSS shouldn't leave a stray empty `<style></style>` block.
+        assert!(!html.contains("<style>\n\n</style>"));
     }
 
     #[test]
     fn test_left_aligned_layout() {
-        let html = generate_html("# Title\n\nParagraph text here.", "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html("# Title\n\nParagraph text here.", "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         // Content should be left-aligned
         assert!(html.contains("text-align: left;"));
         // Paragraphs should also be left-aligned for readability with improved spacing
@@ -710,7 +1688,7 @@ mod tests {
 
     #[test]
     fn test_css_custom_properties() {
-        let html = generate_html("# Test", "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html("# Test", "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         // Test that CSS uses custom properties throughout
         assert!(html.contains("color: var(--text-color);"));
         assert!(html.contains("background-color: var(--bg-color);"));
@@ -724,14 +1702,14 @@ mod tests {
 
     #[test]
     fn test_font_customization_integration() {
-        let html = generate_html("# Test", "18px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html("# Test", "18px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         assert!(html.contains("font-family: sans-serif"));
         assert!(html.contains("font-size: 18px"));
     }
 
     #[test]
     fn test_html_structure_completeness() {
-        let html = generate_html("# Test Title\n\nContent", "16px", "sans-serif", "light", "#3498db", None, None, None);
+        let html = generate_html("# Test Title\n\nContent", "16px", "sans-serif", "light", "#3498db", None, None, None, &[], None, None, "InspiredGitHub", None);
         // Test DOCTYPE and HTML structure
         assert!(html.starts_with("<!DOCTYPE html>"));
         assert!(html.contains("<html lang=\"en\">"));
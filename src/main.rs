@@ -1,8 +1,81 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+use font_kit::source::SystemSource;
+use miette::{miette, Diagnostic, IntoDiagnostic, NamedSource, SourceSpan};
+use notify::{RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
-use statgen::{generate_html, unescape_newlines, validate_color};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+use statgen::{generate_html, unescape_newlines, validate_color, CustomTheme};
+use syntect::highlighting::ThemeSet;
+use thiserror::Error;
+
+// Diagnostics with a precise source span, for the handful of failures where we
+// already hold the offending text (a color value, a config file's contents)
+// and can point straight at it instead of just printing a one-line message.
+#[derive(Debug, Error, Diagnostic)]
+enum CliError {
+    #[error("invalid {flag} color \"{value}\"")]
+    #[diagnostic(help("accepts hex codes like #ff0000/#fff or a CSS color name like \"blue\""))]
+    InvalidColor {
+        flag: &'static str,
+        value: String,
+        #[source_code]
+        src: NamedSource<String>,
+        #[label("not a recognized color")]
+        span: SourceSpan,
+    },
+
+    #[error("failed to parse {file}")]
+    ConfigParse {
+        file: String,
+        #[source_code]
+        src: NamedSource<String>,
+        #[label("{message}")]
+        span: SourceSpan,
+        message: String,
+    },
+
+    #[error("no markdown input provided")]
+    #[diagnostic(help("pass --file <path>, --directory <path>, or --inline <content>"))]
+    MissingInput,
+
+    #[error("could not read markdown file \"{path}\"")]
+    MissingInputFile {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+// Wraps a `validate_color` failure into a diagnostic pointing at the value the
+// user passed for `flag` (a CLI flag or config key name, e.g. "--accent").
+fn invalid_color(flag: &'static str, value: &str) -> miette::Report {
+    CliError::InvalidColor {
+        flag,
+        value: value.to_string(),
+        src: NamedSource::new(flag, value.to_string()),
+        span: (0, value.len()).into(),
+    }
+    .into()
+}
+
+// Turns a serde_json/serde_yaml parse failure into a diagnostic underlining
+// the byte offset the parser reported, so the user sees exactly where their
+// config file went wrong instead of a bare line/column number.
+fn config_parse_error(filename: &str, content: &str, offset: usize, message: String) -> miette::Report {
+    CliError::ConfigParse {
+        file: filename.to_string(),
+        src: NamedSource::new(filename, content.to_string()),
+        span: (offset, 1).into(),
+        message,
+    }
+    .into()
+}
 
 #[derive(Debug, Deserialize, Serialize)]
 struct Config {
@@ -14,35 +87,85 @@ struct Config {
     accent_dark: Option<String>,
     output: Option<String>,
     favicon: Option<String>,
+    // Named custom themes, each a map of CSS custom-property overrides, e.g.
+    // { "solarized": { "--bg-color": "#002b36", "--link-color": "#268bd2" } }
+    custom_themes: Option<HashMap<String, HashMap<String, String>>>,
+    // Arbitrary HTML spliced into `<head>` (analytics snippets, meta tags, web-font links).
+    head_html: Option<String>,
+    // CSS appended after the built-in `<style>` block so it wins ties on specificity.
+    extension_css: Option<String>,
+    // Name of a bundled syntect theme (e.g. "InspiredGitHub", "base16-ocean.dark")
+    // used to color fenced-code-block syntax highlighting.
+    code_theme: Option<String>,
+    // 2+ colors interpolated into a per-heading gradient; a single color
+    // degenerates to the existing solid-accent path.
+    accent_gradient: Option<Vec<String>>,
+}
+
+// Finds the byte offset serde_json/serde_yaml's 1-based `line`/`column` point
+// at, so a parse error can be underlined in the original source text.
+fn line_col_to_byte_offset(src: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = src
+        .split('\n')
+        .take(line.saturating_sub(1))
+        .map(|l| l.len() + 1)
+        .sum();
+    line_start + column.saturating_sub(1)
 }
 
-fn load_config() -> Option<Config> {
+fn load_config() -> miette::Result<Option<Config>> {
     let config_files = ["statgen.json", "statgen.yaml", "statgen.yml"];
 
     for filename in &config_files {
-        if Path::new(filename).exists() {
-            match fs::read_to_string(filename) {
-                Ok(content) => {
-                    if filename.ends_with(".json") {
-                        match serde_json::from_str(&content) {
-                            Ok(config) => return Some(config),
-                            Err(e) => eprintln!("Warning: Failed to parse {}: {}", filename, e),
-                        }
-                    } else {
-                        match serde_yaml::from_str(&content) {
-                            Ok(config) => return Some(config),
-                            Err(e) => eprintln!("Warning: Failed to parse {}: {}", filename, e),
-                        }
-                    }
+        if !Path::new(filename).exists() {
+            continue;
+        }
+        let content = fs::read_to_string(filename).into_diagnostic()?;
+        if filename.ends_with(".json") {
+            return match serde_json::from_str(&content) {
+                Ok(config) => Ok(Some(config)),
+                Err(e) => {
+                    let offset = line_col_to_byte_offset(&content, e.line(), e.column());
+                    Err(config_parse_error(filename, &content, offset, e.to_string()))
                 }
-                Err(e) => eprintln!("Warning: Failed to read {}: {}", filename, e),
-            }
+            };
+        } else {
+            return match serde_yaml::from_str(&content) {
+                Ok(config) => Ok(Some(config)),
+                Err(e) => {
+                    let offset = e
+                        .location()
+                        .map(|loc| loc.index())
+                        .unwrap_or(0);
+                    Err(config_parse_error(filename, &content, offset, e.to_string()))
+                }
+            };
         }
     }
-    None
+    Ok(None)
 }
 
+// CSS generic font-family keywords aren't real installed fonts, so `--font`/
+// `font` values in this set skip the font-kit lookup below.
+const GENERIC_FONT_FAMILIES: &[&str] =
+    &["serif", "sans-serif", "monospace", "cursive", "fantasy", "system-ui"];
 
+// Looks `font` up via font-kit's installed-family list, returning a precise
+// error naming the offending font instead of the old blanket warning.
+fn validate_font(font: &str) -> miette::Result<()> {
+    if GENERIC_FONT_FAMILIES.contains(&font) {
+        return Ok(());
+    }
+    SystemSource::new()
+        .select_family_by_name(font)
+        .map(|_| ())
+        .map_err(|_| {
+            miette!(
+                "Font '{}' was not found on this system. Run `statgen list-fonts` to see installed font families.",
+                font
+            )
+        })
+}
 
 #[derive(Parser)]
 #[command(name = "statgen")]
@@ -50,8 +173,26 @@ fn load_config() -> Option<Config> {
 #[command(long_about = "StatGen converts Markdown files to responsive HTML websites with customizable styling.
 Supports inline input, file processing, batch operations, and configuration files.")]
 struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Convert Markdown to a styled HTML website
+    Build(Box<BuildArgs>),
+    /// List syntax-highlighting theme names available for `--code-theme`
+    ListThemes,
+    /// List font families installed on this system
+    ListFonts,
+    /// Build, then serve the output directory with live reload on Markdown changes
+    Serve(Box<ServeArgs>),
+}
+
+#[derive(Args)]
+struct BuildArgs {
     /// Path to markdown file to process
-    #[arg(short, long, help = "Specify the path to a Markdown file (.md) to convert to HTML")]
+    #[arg(long, help = "Specify the path to a Markdown file (.md) to convert to HTML")]
     file: Option<String>,
 
     /// Directory containing markdown files to process (all .md files)
@@ -66,8 +207,6 @@ struct Cli {
     #[arg(short, long, help = "Directory where the generated HTML file(s) will be saved. Default is \"dist\".")]
     output: Option<String>,
 
-
-
     /// Font size for the website
     #[arg(long, help = "CSS font-size value (e.g., '16px', '1.2em', '14pt')")]
     font_size: Option<String>,
@@ -88,50 +227,91 @@ struct Cli {
     #[arg(long, long_help = "Accent color for dark mode when using auto theme. Accepts color names (red, blue, etc) or hex codes (#ff0000, #3498db)")]
     accent_dark: Option<String>,
 
-    
-
     /// Font family for the website
-    #[arg(short = 'F', long, value_parser = ["Arial", "Helvetica", "Times New Roman", "Georgia", "Verdana", "Courier New", "monospace", "sans-serif", "serif"], help = "Font family for the website. Options: Arial, Helvetica, Times New Roman, Georgia, Verdana, Courier New, monospace, sans-serif, serif")]
+    #[arg(short = 'F', long, long_help = "Font family for the website. Accepts the CSS generic families (serif, sans-serif, monospace, cursive, fantasy, system-ui) or any font family installed on this system - run `statgen list-fonts` to see what's available")]
     font: Option<String>,
 
     /// Emoji for favicon
     #[arg(short = 'f', long, help = "Emoji to use as favicon (e.g., ðŸš€, ðŸ“š, ðŸŒŸ)")]
     favicon: Option<String>,
+
+    /// Syntax-highlighting theme for fenced code blocks
+    #[arg(long, long_help = "Name of a bundled syntect theme used to highlight fenced code blocks (e.g. \"InspiredGitHub\", \"base16-ocean.dark\", \"base16-ocean.light\", \"base16-eighties.dark\", \"base16-mocha.dark\", \"Solarized (dark)\", \"Solarized (light)\"). Unknown names fall back to \"InspiredGitHub\". Run `statgen list-themes` to see what's available")]
+    code_theme: Option<String>,
+
+    /// Comma-separated list of 2+ colors to interpolate into a per-heading gradient
+    #[arg(long, value_delimiter = ',', long_help = "2 or more colors (names or hex codes) to interpolate into a smooth gradient assigned one-per-heading down the page, e.g. --accent-gradient '#ff0000,#0000ff'. A single color falls back to the existing solid accent.")]
+    accent_gradient: Option<Vec<String>>,
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    #[command(flatten)]
+    build: BuildArgs,
+
+    /// Port to serve the generated site on
+    #[arg(long, default_value_t = 4000, help = "TCP port the live-reload dev server listens on")]
+    port: u16,
 }
 
-fn main() -> anyhow::Result<()> {
+fn main() -> miette::Result<()> {
     let cli = Cli::parse();
 
+    match cli.command {
+        Command::ListThemes => {
+            let theme_set = ThemeSet::load_defaults();
+            let mut names: Vec<&String> = theme_set.themes.keys().collect();
+            names.sort();
+            for name in names {
+                println!("{}", name);
+            }
+            Ok(())
+        }
+        Command::ListFonts => {
+            let families = SystemSource::new()
+                .all_families()
+                .map_err(|e| miette!("Error listing system fonts: {}", e))?;
+            let mut families = families;
+            families.sort();
+            for family in families {
+                println!("{}", family);
+            }
+            Ok(())
+        }
+        Command::Build(args) => build_site(&args, None),
+        Command::Serve(args) => run_serve(*args),
+    }
+}
+
+// Builds the site once. `dev_reload_script`, when set, is spliced into
+// `<head>` alongside any configured `head_html` - this is how `serve` gets its
+// live-reload polling script onto every generated page without `generate_html`
+// needing to know about dev mode at all.
+fn build_site(cli: &BuildArgs, dev_reload_script: Option<&str>) -> miette::Result<()> {
     // Load configuration file if present
-    let config = load_config();
+    let config = load_config()?;
 
     // Apply defaults: CLI > Config > Hardcoded defaults
-    let output = cli.output
+    let output = cli.output.clone()
         .or_else(|| config.as_ref().and_then(|c| c.output.clone()))
         .unwrap_or_else(|| "dist".to_string());
 
-    let font_size = cli.font_size
+    let font_size = cli.font_size.clone()
         .or_else(|| config.as_ref().and_then(|c| c.font_size.clone()))
         .unwrap_or_else(|| "16px".to_string());
 
-    let cli_font = cli.font.is_some();
-    let config_font = config.as_ref().and_then(|c| c.font.as_ref()).is_some();
-    let custom_font = cli_font || config_font;
-    
-    let font = cli.font
+    let font = cli.font.clone()
         .or_else(|| config.as_ref().and_then(|c| c.font.clone()))
         .unwrap_or_else(|| "sans-serif".to_string());
 
-    // Show warning if custom font is specified
-    if custom_font {
-        eprintln!("Warning: Make sure font you requested is installed on your system");
-    }
+    // Precisely report a genuinely missing font instead of a blanket warning.
+    validate_font(&font)?;
 
-    let theme = cli.theme
+    let theme = cli.theme.clone()
         .or_else(|| config.as_ref().and_then(|c| c.theme.clone()))
         .unwrap_or_else(|| "auto".to_string());
 
-    let accent = cli.accent
+    let accent = cli.accent.clone()
         .or_else(|| config.as_ref().and_then(|c| c.accent.clone()))
         .unwrap_or_else(|| "#3498db".to_string());
 
@@ -141,52 +321,72 @@ fn main() -> anyhow::Result<()> {
     let accent_dark = cli.accent_dark.as_ref()
         .or_else(|| config.as_ref().and_then(|c| c.accent_dark.as_ref()));
 
-    let favicon = cli.favicon
+    let favicon = cli.favicon.clone()
         .or_else(|| config.as_ref().and_then(|c| c.favicon.clone()));
 
+    let custom_themes: Vec<CustomTheme> = config
+        .as_ref()
+        .and_then(|c| c.custom_themes.clone())
+        .map(|themes| {
+            themes
+                .into_iter()
+                .map(|(name, vars)| CustomTheme { name, vars: vars.into_iter().collect() })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let head_html = match (config.as_ref().and_then(|c| c.head_html.clone()), dev_reload_script) {
+        (Some(configured), Some(script)) => Some(format!("{}\n{}", configured, script)),
+        (Some(configured), None) => Some(configured),
+        (None, Some(script)) => Some(script.to_string()),
+        (None, None) => None,
+    };
+    let extension_css = config.as_ref().and_then(|c| c.extension_css.clone());
+
+    let code_theme = cli.code_theme.clone()
+        .or_else(|| config.as_ref().and_then(|c| c.code_theme.clone()))
+        .unwrap_or_else(|| "InspiredGitHub".to_string());
+
+    let accent_gradient = cli.accent_gradient.clone()
+        .or_else(|| config.as_ref().and_then(|c| c.accent_gradient.clone()));
+
     // Validate accent color
-    if let Err(e) = validate_color(&accent) {
-        eprintln!("Error: {}", e);
-        return Err(anyhow::anyhow!("Invalid accent color"));
+    if validate_color(&accent).is_err() {
+        return Err(invalid_color("--accent", &accent));
     }
 
     // Validate accent_light color
     if let Some(accent_light) = accent_light {
-        if let Err(e) = validate_color(accent_light) {
-            eprintln!("Error: {}", e);
-            return Err(anyhow::anyhow!("Invalid accent-light color"));
+        if validate_color(accent_light).is_err() {
+            return Err(invalid_color("--accent-light", accent_light));
         }
     }
 
     // Validate accent_dark color
     if let Some(accent_dark) = accent_dark {
-        if let Err(e) = validate_color(accent_dark) {
-            eprintln!("Error: {}", e);
-            return Err(anyhow::anyhow!("Invalid accent-dark color"));
+        if validate_color(accent_dark).is_err() {
+            return Err(invalid_color("--accent-dark", accent_dark));
         }
     }
 
-
-
-    match fs::create_dir_all(&output) {
-        Ok(_) => {},
-        Err(e) => {
-            eprintln!("Error creating output directory '{}': {}", output, e);
-            return Err(e.into());
+    // Validate every accent_gradient stop
+    if let Some(stops) = &accent_gradient {
+        for stop in stops {
+            if validate_color(stop).is_err() {
+                return Err(invalid_color("--accent-gradient", stop));
+            }
         }
     }
 
-    if let Some(dir_path) = cli.directory {
+    fs::create_dir_all(&output)
+        .map_err(|e| miette!("Error creating output directory '{}': {}", output, e))?;
+
+    if let Some(dir_path) = cli.directory.clone() {
                 // Batch processing: process all .md files in directory
                 println!("Processing all .md files in directory: {}", dir_path);
 
-                let dir_entries = match fs::read_dir(&dir_path) {
-                    Ok(entries) => entries,
-                    Err(e) => {
-                        eprintln!("Error reading directory '{}': {}", dir_path, e);
-                        return Err(e.into());
-                    }
-                };
+                let dir_entries = fs::read_dir(&dir_path)
+                    .map_err(|e| miette!("Error reading directory '{}': {}", dir_path, e))?;
 
                 let mut processed_count = 0;
                 for entry in dir_entries {
@@ -217,7 +417,7 @@ fn main() -> anyhow::Result<()> {
                                 }
                             };
 
-let html_content = generate_html(&markdown_content, &font_size, &font, &theme, &accent, accent_light.map(|s| s.as_str()), accent_dark.map(|s| s.as_str()), favicon.as_deref());
+let html_content = generate_html(&markdown_content, &font_size, &font, &theme, &accent, accent_light.map(|s| s.as_str()), accent_dark.map(|s| s.as_str()), favicon.as_deref(), &custom_themes, head_html.as_deref(), extension_css.as_deref(), &code_theme, accent_gradient.as_deref());
                             let output_filename = format!("{}.html", file_name);
                             let output_path = Path::new(&output).join(output_filename);
 
@@ -234,8 +434,6 @@ let html_content = generate_html(&markdown_content, &font_size, &font, &theme, &
                     }
                 }
 
-
-
                 if processed_count == 0 {
                     println!("No .md files found in directory '{}'", dir_path);
                 } else {
@@ -243,40 +441,193 @@ let html_content = generate_html(&markdown_content, &font_size, &font, &theme, &
                 }
     } else {
         // Single file or inline processing
-        let markdown_content = if let Some(file_path) = cli.file {
+        let markdown_content = if let Some(file_path) = cli.file.clone() {
             println!("Reading markdown from file: {}", file_path);
-            match fs::read_to_string(&file_path) {
-                Ok(content) => content,
-                Err(e) => {
-                    eprintln!("Error reading file '{}': {}", file_path, e);
-                    return Err(e.into());
-                }
-            }
-        } else if let Some(content) = cli.inline {
+            fs::read_to_string(&file_path).map_err(|e| CliError::MissingInputFile {
+                path: file_path.clone(),
+                source: e,
+            })?
+        } else if let Some(content) = cli.inline.clone() {
             println!("Using inline markdown content");
             // Handle both escaped and actual newlines
             unescape_newlines(&content)
                 .replace("`n", "\n")   // PowerShell style
         } else {
-            return Err(anyhow::anyhow!("Error: Either --file, --directory, or --inline must be provided. Use statgen --help for help"));
+            return Err(CliError::MissingInput.into());
         };
 
         println!("Markdown content length: {} characters", markdown_content.len());
 
-        let html_content = generate_html(&markdown_content, &font_size, &font, &theme, &accent, accent_light.map(|s| s.as_str()), accent_dark.map(|s| s.as_str()), favicon.as_deref());
+        let html_content = generate_html(&markdown_content, &font_size, &font, &theme, &accent, accent_light.map(|s| s.as_str()), accent_dark.map(|s| s.as_str()), favicon.as_deref(), &custom_themes, head_html.as_deref(), extension_css.as_deref(), &code_theme, accent_gradient.as_deref());
         let output_path = Path::new(&output).join("index.html");
 
-        match fs::write(&output_path, html_content) {
-            Ok(_) => {},
-            Err(e) => {
-                eprintln!("Error writing to '{}': {}", output_path.display(), e);
-                return Err(e.into());
+        fs::write(&output_path, html_content)
+            .map_err(|e| miette!("Error writing to '{}': {}", output_path.display(), e))?;
+
+        println!("âœ“ Static site generated successfully at: {}", output_path.display());
+    }
+
+    Ok(())
+}
+
+// Long-polling reload script: it asks `/__statgen_reload/<version>` for the
+// build version it last saw and reloads the page as soon as the server
+// reports a newer one, giving a live-reload authoring loop without websockets.
+// Starts polling from `since`, the version of the build the page is part of, so
+// `run_serve` must publish that same version once the build is written or every
+// fresh page load would see an immediate "newer" version and reload in a loop.
+fn reload_script(since: u64) -> String {
+    let mut script = r#"<script>
+(function poll(since) {
+    fetch('/__statgen_reload/' + since)
+        .then(function (res) { return res.text(); })
+        .then(function (body) {
+            var version = parseInt(body, 10);
+            if (version !== since) {
+                location.reload();
+            } else {
+                poll(version);
             }
+        })
+        .catch(function () { setTimeout(function () { poll(since); }, 1000); });
+})("#
+        .to_string();
+    script.push_str(&format!("{since});\n</script>"));
+    script
+}
+
+// Blocks until `reload_version` differs from `since` or 30s have passed,
+// whichever comes first, then returns the current version.
+fn await_reload(reload_version: &AtomicU64, since: u64) -> u64 {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    loop {
+        let current = reload_version.load(Ordering::SeqCst);
+        if current != since || Instant::now() >= deadline {
+            return current;
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+}
+
+fn handle_request(request: tiny_http::Request, output_dir: &str, reload_version: &AtomicU64) {
+    let url = request.url().to_string();
+
+    if let Some(since) = url
+        .strip_prefix("/__statgen_reload/")
+        .and_then(|s| s.parse::<u64>().ok())
+    {
+        let current = await_reload(reload_version, since);
+        let _ = request.respond(tiny_http::Response::from_string(current.to_string()));
+        return;
+    }
+
+    // Reject `..` segments so a request can't escape `output_dir`.
+    if url.contains("..") {
+        let _ = request.respond(
+            tiny_http::Response::from_string("403 Forbidden").with_status_code(403),
+        );
+        return;
+    }
+
+    let rel_path = if url == "/" { "index.html" } else { url.trim_start_matches('/') };
+    let file_path = Path::new(output_dir).join(rel_path);
+
+    match fs::read(&file_path) {
+        Ok(contents) => {
+            let _ = request.respond(tiny_http::Response::from_data(contents));
+        }
+        Err(_) => {
+            let _ = request.respond(
+                tiny_http::Response::from_string("404 Not Found").with_status_code(404),
+            );
         }
+    }
+}
 
+fn run_serve(args: ServeArgs) -> miette::Result<()> {
+    let ServeArgs { build, port } = args;
+
+    let watch_path = build
+        .directory
+        .clone()
+        .or_else(|| {
+            build
+                .file
+                .as_ref()
+                .and_then(|f| Path::new(f).parent())
+                .map(|p| p.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| ".".to_string());
+
+    println!("Building...");
+    build_site(&build, Some(&reload_script(0)))?;
+
+    // Same CLI > Config > default precedence `build_site` used to pick where it wrote.
+    let output_dir = match build.output.clone() {
+        Some(output) => output,
+        None => load_config()?.and_then(|c| c.output).unwrap_or_else(|| "dist".to_string()),
+    };
+
+    // Must match the `since` value the initial build's `reload_script(0)` poll
+    // loop starts from, or the first poll after every page load sees a version
+    // mismatch and reloads immediately - forever.
+    let reload_version = Arc::new(AtomicU64::new(0));
+
+    let server = tiny_http::Server::http(format!("127.0.0.1:{}", port))
+        .map_err(|e| miette!("Failed to start server on port {}: {}", port, e))?;
+    println!("Serving '{}' at http://127.0.0.1:{}", output_dir, port);
+
+    {
+        let server = Arc::new(server);
+        let reload_version = Arc::clone(&reload_version);
+        let output_dir = output_dir.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let reload_version = Arc::clone(&reload_version);
+                let output_dir = output_dir.clone();
+                thread::spawn(move || handle_request(request, &output_dir, &reload_version));
+            }
+        });
+    }
+
+    println!("Watching '{}' for Markdown changes...", watch_path);
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| miette!("Failed to start filesystem watcher: {}", e))?;
+    watcher
+        .watch(Path::new(&watch_path), RecursiveMode::Recursive)
+        .map_err(|e| miette!("Failed to watch '{}': {}", watch_path, e))?;
+
+    for result in rx {
+        let event = match result {
+            Ok(event) => event,
+            Err(e) => {
+                eprintln!("Watch error: {}", e);
+                continue;
+            }
+        };
 
+        let touched_markdown = event
+            .paths
+            .iter()
+            .any(|p| p.extension().is_some_and(|ext| ext == "md"));
+        if !touched_markdown {
+            continue;
+        }
 
-        println!("âœ“ Static site generated successfully at: {}", output_path.display());
+        // Deliberately a full rebuild rather than regenerating just the touched
+        // file: `build_site` is cheap enough for this crate's typical input
+        // sizes that the simplicity of always reusing the single build path
+        // outweighs the complexity of tracking per-file incremental state.
+        println!("Change detected, rebuilding...");
+        let next_version = reload_version.load(Ordering::SeqCst) + 1;
+        match build_site(&build, Some(&reload_script(next_version))) {
+            Ok(_) => {
+                reload_version.store(next_version, Ordering::SeqCst);
+                println!("âœ“ Rebuilt");
+            }
+            Err(e) => eprintln!("Error rebuilding: {}", e),
+        }
     }
 
     Ok(())